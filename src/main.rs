@@ -1,12 +1,18 @@
 use sdl2::audio::{AudioCallback, AudioSpecDesired};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::Color;
-use sdl2::rect::{Point, Rect};
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
 use sdl2::render::{Texture, TextureCreator, TextureQuery};
 use sdl2::ttf::Font;
 use sdl2::video::WindowContext;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 // handle the annoying Rect i32
@@ -17,66 +23,544 @@ macro_rules! rect(
 );
 
 const BORDER: u32 = 40;
-const NUM_SQUAREWAVES: usize = 12;
-const NUM_MAP: [(Keycode, usize); 9] = [
-    (Keycode::Num1, 0),
-    (Keycode::Num2, 1),
-    (Keycode::Num3, 2),
-    (Keycode::Num4, 3),
-    (Keycode::Num5, 4),
-    (Keycode::Num6, 5),
-    (Keycode::Num7, 6),
-    (Keycode::Num8, 7),
-    (Keycode::Num9, 8),
+// Logical size of the offscreen framebuffer the scene is drawn into. The window
+// opens at PIXEL_SCALE times this size, and the framebuffer is stretched to fill
+// the window (also after a resize); the FPS text is drawn at window resolution.
+const FB_WIDTH: u32 = 256;
+const FB_HEIGHT: u32 = 240;
+const PIXEL_SCALE: u32 = 3;
+// The 1-9 keys each map to a note frequency in Hz; the mixer converts these to
+// per-sample phase increments once the device's sample rate is known.
+const NOTES: [(Keycode, f32); 9] = [
+    (Keycode::Num1, 349.24),
+    (Keycode::Num2, 392.00),
+    (Keycode::Num3, 440.00),
+    (Keycode::Num4, 493.92),
+    (Keycode::Num5, 523.28),
+    (Keycode::Num6, 587.36),
+    (Keycode::Num7, 659.28),
+    (Keycode::Num8, 698.48),
+    (Keycode::Num9, 784.00),
 ];
 const VOLUME: f32 = 0.01;
 
-struct SquareWave {
-    phase: [f32; NUM_SQUAREWAVES],
-    phase_inc: [f32; NUM_SQUAREWAVES],
-    volume: [f32; NUM_SQUAREWAVES],
+// Per-voice amplitude envelope stage. The gain ramps Off -> Attack -> Decay ->
+// Sustain while a key is held, then Release -> Off once it is let go, which
+// keeps key presses from clicking on the instant amplitude jump.
+#[derive(Clone, Copy, PartialEq)]
+enum EnvState {
+    Off,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
 }
 
-impl SquareWave {
-    fn new() -> SquareWave {
-        SquareWave {
-            phase: [0.0; NUM_SQUAREWAVES],
-            phase_inc: [0.0; NUM_SQUAREWAVES],
-            volume: [0.0; NUM_SQUAREWAVES],
+// The timbre a voice is synthesized with. All four are derived from the same
+// normalized 0..1 phase so they can share the oscillator and envelope path.
+#[derive(Clone, Copy, PartialEq)]
+enum WaveKind {
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+impl WaveKind {
+    // Sample this waveform at a normalized phase in 0..1.
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            WaveKind::Square => {
+                if phase <= 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            WaveKind::Sine => (phase * 2.0 * PI).sin(),
+            WaveKind::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            WaveKind::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+
+    // The next waveform in the cycle, used by the toggle key.
+    fn next(self) -> WaveKind {
+        match self {
+            WaveKind::Square => WaveKind::Sine,
+            WaveKind::Sine => WaveKind::Triangle,
+            WaveKind::Triangle => WaveKind::Sawtooth,
+            WaveKind::Sawtooth => WaveKind::Square,
+        }
+    }
+
+    // Parse a waveform name as used in a pattern row.
+    fn parse(token: &str) -> Option<WaveKind> {
+        match token {
+            "square" => Some(WaveKind::Square),
+            "sine" => Some(WaveKind::Sine),
+            "triangle" => Some(WaveKind::Triangle),
+            "saw" | "sawtooth" => Some(WaveKind::Sawtooth),
+            _ => None,
         }
     }
+}
+
+// How many pattern rows the sequencer plays per second; `frames_per_row` is
+// derived from this and the device sample rate.
+const ROWS_PER_SECOND: f32 = 8.0;
 
-    fn set_freq(&mut self, freq: f32) {
-        self.phase_inc[0] = 349.24 / freq;
-        self.phase_inc[1] = 392.00 / freq;
-        self.phase_inc[2] = 440.00 / freq;
-        self.phase_inc[3] = 493.92 / freq;
-        self.phase_inc[4] = 523.28 / freq;
-        self.phase_inc[5] = 587.36 / freq;
-        self.phase_inc[6] = 659.28 / freq;
-        self.phase_inc[7] = 698.48 / freq;
-        self.phase_inc[8] = 784.00 / freq;
+// A short built-in tune used when no pattern file is present on disk.
+const DEFAULT_PATTERN: &str = "\
+# astralix pattern: one row per tick, `---` is a rest
+C4 square 2
+E4 square 2
+G4 square 2
+C5 square 4
+---
+C4,E4,G4 triangle 4
+---
+";
+
+// Resolve a pattern token to a frequency in Hz. Accepts either a raw number
+// (e.g. `440`) or an equal-tempered note name (e.g. `C4`, `A#4`, `Bb3`).
+fn note_freq(token: &str) -> Option<f32> {
+    if let Ok(freq) = token.parse::<f32>() {
+        return Some(freq);
+    }
+    let bytes = token.as_bytes();
+    let mut semitone: i32 = match bytes.first()?.to_ascii_uppercase() {
+        b'C' => 0,
+        b'D' => 2,
+        b'E' => 4,
+        b'F' => 5,
+        b'G' => 7,
+        b'A' => 9,
+        b'B' => 11,
+        _ => return None,
+    };
+    let mut rest = &token[1..];
+    if let Some(stripped) = rest.strip_prefix('#') {
+        semitone += 1;
+        rest = stripped;
+    } else if let Some(stripped) = rest.strip_prefix('b') {
+        semitone -= 1;
+        rest = stripped;
     }
+    let octave: i32 = rest.parse().ok()?;
+    let midi = (octave + 1) * 12 + semitone;
+    Some(440.0 * 2.0_f32.powf((midi - 69) as f32 / 12.0))
+}
+
+// One tick of a pattern: the notes struck on this row (empty for a rest), the
+// waveform to play them on, and how many rows they are held before release.
+#[derive(Clone)]
+struct Row {
+    notes: Vec<f32>,
+    wave: WaveKind,
+    gate: u32,
+}
+
+// Plays a loaded pattern on the audio thread, advancing one row every
+// `frames_per_row` samples and triggering its notes through the mixer.
+struct Sequencer {
+    rows: Vec<Row>,
+    cursor: usize,
+    frame: u32,
+    frames_per_row: u32,
+    looping: bool,
+    // Tracks struck by earlier rows, with the number of rows left before they
+    // are released.
+    active: Vec<(TrackID, u32)>,
 }
 
-impl AudioCallback for &mut SquareWave {
+impl Sequencer {
+    // Parse a pattern: one row per line, `#` lines and blanks ignored. A row is
+    // `notes [wave] [gate]`, where `notes` is comma-separated note names/freqs
+    // or `---` for a rest.
+    fn load(text: &str) -> Sequencer {
+        let mut rows = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let head = fields.next().unwrap_or("---");
+            let notes = if head == "---" {
+                Vec::new()
+            } else {
+                head.split(',').filter_map(note_freq).collect()
+            };
+            let mut wave = WaveKind::Square;
+            let mut gate = 1;
+            for field in fields {
+                if let Some(w) = WaveKind::parse(field) {
+                    wave = w;
+                } else if let Ok(g) = field.parse::<u32>() {
+                    gate = g.max(1);
+                }
+            }
+            rows.push(Row { notes, wave, gate });
+        }
+        Sequencer {
+            rows,
+            cursor: 0,
+            frame: 0,
+            frames_per_row: (44_100.0 / ROWS_PER_SECOND) as u32,
+            looping: true,
+            active: Vec::new(),
+        }
+    }
+}
+
+// A single sounding voice: an oscillator at a fixed phase increment plus its
+// own ADSR envelope. Tracks are created and freed on demand by the mixer, so
+// the same note can sound several times at once.
+struct Track {
+    phase: f32,
+    phase_inc: f32,
+    wave: WaveKind,
+    env: EnvState,
+    gain: f32,
+    sustain_level: f32,
+    attack_rate: f32,
+    decay_rate: f32,
+    release_rate: f32,
+}
+
+impl Track {
+    fn new(phase_inc: f32, wave: WaveKind, sample_rate: f32) -> Track {
+        Track {
+            phase: 0.0,
+            phase_inc,
+            wave,
+            env: EnvState::Attack,
+            gain: 0.0,
+            sustain_level: 0.7,
+            attack_rate: 1.0 / (0.01 * sample_rate),
+            decay_rate: 1.0 / (0.10 * sample_rate),
+            release_rate: 1.0 / (0.20 * sample_rate),
+        }
+    }
+
+    // Move the voice into its release tail; it keeps sounding until the gain
+    // reaches zero, at which point the mixer frees it.
+    fn release(&mut self) {
+        if self.env != EnvState::Off {
+            self.env = EnvState::Release;
+        }
+    }
+
+    fn is_off(&self) -> bool {
+        self.env == EnvState::Off
+    }
+
+    // Produce one enveloped sample and advance phase and envelope by a frame.
+    fn next_sample(&mut self) -> f32 {
+        let osc = self.wave.sample(self.phase);
+        let out = osc * self.gain;
+        self.phase = (self.phase + self.phase_inc) % 1.0;
+        self.step_env();
+        out
+    }
+
+    fn step_env(&mut self) {
+        match self.env {
+            EnvState::Off => {}
+            EnvState::Attack => {
+                self.gain += self.attack_rate;
+                if self.gain >= 1.0 {
+                    self.gain = 1.0;
+                    self.env = EnvState::Decay;
+                }
+            }
+            EnvState::Decay => {
+                self.gain -= self.decay_rate;
+                if self.gain <= self.sustain_level {
+                    self.gain = self.sustain_level;
+                    self.env = EnvState::Sustain;
+                }
+            }
+            EnvState::Sustain => {}
+            EnvState::Release => {
+                self.gain -= self.release_rate;
+                if self.gain <= 0.0 {
+                    self.gain = 0.0;
+                    self.env = EnvState::Off;
+                }
+            }
+        }
+    }
+}
+
+// Handle for a track living on the audio thread, returned to the caller so it
+// can later stop the note it started.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct TrackID(u64);
+
+// Allocates unique `TrackID`s; shared between the event loop and the audio
+// thread so both can mint ids without coordinating.
+#[derive(Clone)]
+struct TrackIDs(Arc<AtomicU64>);
+
+impl TrackIDs {
+    fn new() -> TrackIDs {
+        TrackIDs(Arc::new(AtomicU64::new(0)))
+    }
+
+    fn next(&self) -> TrackID {
+        TrackID(self.0.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// Commands sent from the event loop to the mixer running on the audio thread.
+// The caller allocates the `TrackID` itself so note-ons never block on the
+// audio thread.
+enum MixerRequest {
+    PlayNote { id: TrackID, freq: f32, wave: WaveKind },
+    StopNote { id: TrackID },
+    SetPlayback(bool),
+    SetLooping(bool),
+}
+
+// Shared between the audio thread and the event loop: while `recording` is set
+// the callback appends its mixed samples to `buffer`, which the event loop
+// drains and writes to disk when recording is toggled off.
+struct Capture {
+    recording: bool,
+    buffer: Vec<f32>,
+}
+
+impl Capture {
+    fn new() -> Capture {
+        Capture {
+            recording: false,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+// Owns the live tracks and mixes them on the audio thread. It pulls commands
+// off `requests`, sums every track's contribution into the output buffer, and
+// frees tracks whose envelope has returned to `Off`.
+struct Mixer {
+    tracks: HashMap<TrackID, Track>,
+    ids: TrackIDs,
+    sample_rate: f32,
+    requests: Receiver<MixerRequest>,
+    capture: Arc<Mutex<Capture>>,
+    sequencer: Option<Sequencer>,
+    playback: bool,
+}
+
+impl Mixer {
+    fn new(
+        requests: Receiver<MixerRequest>,
+        ids: TrackIDs,
+        capture: Arc<Mutex<Capture>>,
+        sequencer: Sequencer,
+    ) -> Mixer {
+        Mixer {
+            tracks: HashMap::new(),
+            ids,
+            sample_rate: 44_100.0,
+            requests,
+            capture,
+            sequencer: Some(sequencer),
+            playback: false,
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        if let Some(seq) = self.sequencer.as_mut() {
+            seq.frames_per_row = (sample_rate / ROWS_PER_SECOND) as u32;
+        }
+    }
+
+    // Start a track for `freq`/`wave` under the caller-supplied id.
+    fn spawn_track(&mut self, id: TrackID, freq: f32, wave: WaveKind) {
+        let track = Track::new(freq / self.sample_rate, wave, self.sample_rate);
+        self.tracks.insert(id, track);
+    }
+
+    fn release_track(&mut self, id: TrackID) {
+        if let Some(track) = self.tracks.get_mut(&id) {
+            track.release();
+        }
+    }
+
+    // Drain every pending command before rendering the next buffer.
+    fn handle_requests(&mut self) {
+        while let Ok(req) = self.requests.try_recv() {
+            match req {
+                MixerRequest::PlayNote { id, freq, wave } => self.spawn_track(id, freq, wave),
+                MixerRequest::StopNote { id } => self.release_track(id),
+                MixerRequest::SetPlayback(on) => {
+                    self.playback = on;
+                    if let Some(seq) = self.sequencer.as_mut() {
+                        seq.cursor = 0;
+                        seq.frame = 0;
+                        let active: Vec<TrackID> =
+                            seq.active.drain(..).map(|(id, _)| id).collect();
+                        for id in active {
+                            self.release_track(id);
+                        }
+                    }
+                }
+                MixerRequest::SetLooping(on) => {
+                    if let Some(seq) = self.sequencer.as_mut() {
+                        seq.looping = on;
+                    }
+                }
+            }
+        }
+    }
+
+    // Advance the sequencer by one frame, triggering the next row and releasing
+    // gated notes when a row boundary is crossed.
+    fn advance_sequencer(&mut self) {
+        let Some(mut seq) = self.sequencer.take() else {
+            return;
+        };
+        seq.frame += 1;
+        if seq.frame >= seq.frames_per_row {
+            seq.frame = 0;
+            let mut still = Vec::with_capacity(seq.active.len());
+            for (id, rem) in std::mem::take(&mut seq.active) {
+                let rem = rem - 1;
+                if rem == 0 {
+                    self.release_track(id);
+                } else {
+                    still.push((id, rem));
+                }
+            }
+            seq.active = still;
+            if seq.cursor >= seq.rows.len() && seq.looping {
+                seq.cursor = 0;
+            }
+            if seq.cursor < seq.rows.len() {
+                let row = seq.rows[seq.cursor].clone();
+                seq.cursor += 1;
+                for &freq in &row.notes {
+                    let id = self.ids.next();
+                    self.spawn_track(id, freq, row.wave);
+                    seq.active.push((id, row.gate));
+                }
+            }
+        }
+        self.sequencer = Some(seq);
+    }
+}
+
+impl AudioCallback for &mut Mixer {
     type Channel = f32;
 
     fn callback(&mut self, channels: &mut [f32]) {
+        self.handle_requests();
         for channel in channels.iter_mut() {
-            *channel = 0.0;
-            for sw in 0..NUM_SQUAREWAVES {
-                *channel += if self.phase[sw] <= 0.5 {
-                    self.volume[sw]
-                } else {
-                    -self.volume[sw]
-                };
-                self.phase[sw] = (self.phase[sw] + self.phase_inc[sw]) % 1.0;
+            if self.playback {
+                self.advance_sequencer();
+            }
+            let mut sample = 0.0;
+            for track in self.tracks.values_mut() {
+                sample += track.next_sample() * VOLUME;
+            }
+            *channel = sample;
+        }
+        self.tracks.retain(|_, track| !track.is_off());
+        if let Ok(mut cap) = self.capture.lock() {
+            if cap.recording {
+                cap.buffer.extend_from_slice(channels);
             }
         }
     }
 }
 
+// Write mono `samples` out as a 16-bit PCM WAV file at `sample_rate`.
+fn write_wav(path: &str, samples: &[f32], sample_rate: u32) -> Result<(), String> {
+    let mut file = File::create(path).map_err(|e| e.to_string())?;
+    let data_len = (samples.len() * 2) as u32;
+    let byte_rate = sample_rate * 2; // 1 channel * 2 bytes/sample
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&1u16.to_le_bytes()); // mono
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes()); // block align
+    header.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    file.write_all(&header).map_err(|e| e.to_string())?;
+
+    let mut pcm = Vec::with_capacity(samples.len() * 2);
+    for &s in samples {
+        let clamped = s.clamp(-1.0, 1.0);
+        pcm.extend_from_slice(&((clamped * i16::MAX as f32) as i16).to_le_bytes());
+    }
+    file.write_all(&pcm).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// An RGB24 software framebuffer at the fixed logical resolution. Drawing goes
+// through `put`; each frame the raw bytes are uploaded to a streaming texture
+// and stretched to the window, decoupling logical resolution from window size.
+struct Framebuffer {
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    fn new() -> Framebuffer {
+        Framebuffer {
+            pixels: vec![0; (FB_WIDTH * FB_HEIGHT * 3) as usize],
+        }
+    }
+
+    // Bytes per row, as expected by the streaming texture upload.
+    fn pitch() -> usize {
+        (FB_WIDTH * 3) as usize
+    }
+
+    fn clear(&mut self, color: Color) {
+        for px in self.pixels.chunks_exact_mut(3) {
+            px[0] = color.r;
+            px[1] = color.g;
+            px[2] = color.b;
+        }
+    }
+
+    // Plot a single pixel, silently clipping coordinates outside the buffer.
+    fn put(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= FB_WIDTH as i32 || y >= FB_HEIGHT as i32 {
+            return;
+        }
+        let idx = (y as u32 * FB_WIDTH + x as u32) as usize * 3;
+        self.pixels[idx] = color.r;
+        self.pixels[idx + 1] = color.g;
+        self.pixels[idx + 2] = color.b;
+    }
+
+    // Plot a line between two points with a simple DDA, built on `put`.
+    fn line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs());
+        if steps == 0 {
+            self.put(x0, y0, color);
+            return;
+        }
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let x = x0 as f32 + (x1 - x0) as f32 * t;
+            let y = y0 as f32 + (y1 - y0) as f32 * t;
+            self.put(x.round() as i32, y.round() as i32, color);
+        }
+    }
+}
+
 fn get_text<'a>(
     f: &Font,
     s: &str,
@@ -102,7 +586,11 @@ fn main() -> Result<(), String> {
     let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
 
     let window = video_subsystem
-        .window("rust-sdl2 demo", 800, 600)
+        .window(
+            "rust-sdl2 demo",
+            FB_WIDTH * PIXEL_SCALE,
+            FB_HEIGHT * PIXEL_SCALE,
+        )
         .position_centered()
         .resizable()
         .build()
@@ -114,6 +602,10 @@ fn main() -> Result<(), String> {
         .map_err(|e| format!("could not make a canvas: {}", e.to_string()))?;
 
     let texture_creator = canvas.texture_creator();
+    let mut fb_texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGB24, FB_WIDTH, FB_HEIGHT)
+        .map_err(|e| e.to_string())?;
+    let mut fb = Framebuffer::new();
     let font = ttf_context.load_font(
         "/home/monkey/.fonts/System San Francisco Display Regular.ttf",
         16,
@@ -125,18 +617,30 @@ fn main() -> Result<(), String> {
         samples: None,     // default sample size
     };
 
-    let mut sw = SquareWave::new();
+    let (request_tx, request_rx) = std::sync::mpsc::channel::<MixerRequest>();
+    let ids = TrackIDs::new();
+    let capture = Arc::new(Mutex::new(Capture::new()));
+    let pattern = std::fs::read_to_string("song.txt").unwrap_or_else(|_| DEFAULT_PATTERN.to_string());
+    let sequencer = Sequencer::load(&pattern);
+    let mut mixer = Mixer::new(request_rx, ids.clone(), Arc::clone(&capture), sequencer);
 
     let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
-        sw.set_freq(spec.freq as f32);
-        &mut sw
+        mixer.set_sample_rate(spec.freq as f32);
+        &mut mixer
     })?;
 
+    let sample_rate = device.spec().freq as u32;
     device.resume();
 
     let mut fps_vec = VecDeque::new();
     let mut prev_stamp = Instant::now();
     let mut event_pump = sdl_context.event_pump()?;
+    // Currently selected timbre for new notes, and the tracks held down per key.
+    let mut active_wave = WaveKind::Square;
+    let mut held: HashMap<Keycode, TrackID> = HashMap::new();
+    let mut recording_count = 0;
+    let mut playback = false;
+    let mut looping = true;
     let mut i = 0;
     'running: loop {
         let curr_stamp = Instant::now();
@@ -147,22 +651,21 @@ fn main() -> Result<(), String> {
         let fps: f64 = fps_vec.iter().sum::<f64>() / fps_vec.len() as f64;
 
         i = (i + 1) % 255;
-        canvas.set_draw_color(Color::RGB(i, 64, 255 - i));
-        canvas.clear();
-        canvas.set_draw_color(Color::RGB(0, 128, 0));
-
-        let (width, height) = canvas.output_size()?;
-        canvas.fill_rect(rect!(
-            BORDER,
-            BORDER,
-            width - (BORDER * 2),
-            height - (BORDER * 2)
-        ))?;
-
-        let p1 = Point::new(100, 200);
-        let p2 = Point::new(300, 400);
-        canvas.set_draw_color(Color::RGB(128, 0, 0));
-        canvas.draw_line(p1, p2)?;
+        fb.clear(Color::RGB(i, 64, 255 - i));
+
+        let b = BORDER as i32;
+        for y in b..(FB_HEIGHT as i32 - b) {
+            for x in b..(FB_WIDTH as i32 - b) {
+                fb.put(x, y, Color::RGB(0, 128, 0));
+            }
+        }
+        fb.line(40, 60, 200, 180, Color::RGB(128, 0, 0));
+
+        fb_texture
+            .update(None, &fb.pixels, Framebuffer::pitch())
+            .map_err(|e| e.to_string())?;
+        canvas.copy(&fb_texture, None, None)?;
+
         let (text_texture, text_width, text_height) = get_text(
             &font,
             format!("FPS: {fps:.1}").as_str(),
@@ -181,6 +684,55 @@ fn main() -> Result<(), String> {
                 } => {
                     break 'running;
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Tab),
+                    repeat: false,
+                    ..
+                } => {
+                    active_wave = active_wave.next();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::R),
+                    repeat: false,
+                    ..
+                } => {
+                    let mut cap = capture.lock().map_err(|e| e.to_string())?;
+                    if cap.recording {
+                        cap.recording = false;
+                        let samples = std::mem::take(&mut cap.buffer);
+                        drop(cap);
+                        let path = format!("recording-{recording_count}.wav");
+                        recording_count += 1;
+                        write_wav(&path, &samples, sample_rate)?;
+                        println!("wrote {} samples to {path}", samples.len());
+                    } else {
+                        cap.buffer.clear();
+                        cap.recording = true;
+                        println!("recording...");
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    repeat: false,
+                    ..
+                } => {
+                    playback = !playback;
+                    request_tx
+                        .send(MixerRequest::SetPlayback(playback))
+                        .map_err(|e| e.to_string())?;
+                    println!("playback: {playback}");
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::L),
+                    repeat: false,
+                    ..
+                } => {
+                    looping = !looping;
+                    request_tx
+                        .send(MixerRequest::SetLooping(looping))
+                        .map_err(|e| e.to_string())?;
+                    println!("looping: {looping}");
+                }
                 Event::KeyDown {
                     keycode:
                         Some(
@@ -197,9 +749,17 @@ fn main() -> Result<(), String> {
                     repeat: false,
                     ..
                 } => {
-                    for (key, num) in NUM_MAP {
+                    for (key, freq) in NOTES {
                         if keycode == key {
-                            sw.volume[num] = VOLUME;
+                            let id = ids.next();
+                            request_tx
+                                .send(MixerRequest::PlayNote {
+                                    id,
+                                    freq,
+                                    wave: active_wave,
+                                })
+                                .map_err(|e| e.to_string())?;
+                            held.insert(keycode, id);
                         }
                     }
                 }
@@ -219,10 +779,10 @@ fn main() -> Result<(), String> {
                     repeat: false,
                     ..
                 } => {
-                    for (key, num) in NUM_MAP {
-                        if keycode == key {
-                            sw.volume[num] = 0.00;
-                        }
+                    if let Some(id) = held.remove(&keycode) {
+                        request_tx
+                            .send(MixerRequest::StopNote { id })
+                            .map_err(|e| e.to_string())?;
                     }
                 }
                 _ => {}